@@ -0,0 +1,70 @@
+//! Macros that wrap a block of code in a span and a duration histogram, so
+//! callers can add telemetry to their own functions in one line instead of
+//! the manual boilerplate shown in `emit_span` and `emit_metrics`.
+
+/// Run `$body` inside a span named `$name` on the global tracer, tagging
+/// attributes from a braced list of `$key => $value` pairs (`$key` is a bare field name, as in
+/// `tracing::error!(user_name = "...")`), and setting the span status to
+/// `Error` if `$body` evaluates to `Result::Err`.
+///
+/// ```ignore
+/// let result: Result<(), std::io::Error> = traced!("read-config", { path => "demo.toml" }, {
+///     Ok(())
+/// });
+/// ```
+///
+macro_rules! traced {
+    ($name:expr, { $($key:ident => $value:expr),* $(,)? }, $body:block) => {{
+        use opentelemetry::{global, trace::{Status, TraceContextExt, Tracer}, KeyValue};
+
+        let tracer = global::tracer(module_path!());
+        tracer.in_span($name, |cx| {
+            let span = cx.span();
+            $( span.set_attribute(KeyValue::new(stringify!($key), $value)); )*
+            let result = (|| $body)();
+            match &result {
+                Ok(_) => span.set_status(Status::Ok),
+                Err(err) => span.set_status(Status::error(err.to_string())),
+            }
+            result
+        })
+    }};
+}
+pub(crate) use traced;
+
+/// Like `traced!`, but also records `$body`'s wall-clock duration, in
+/// seconds, into a histogram named `$name` on the global meter. `$key` is a
+/// bare field name, as in `tracing::error!(user_name = "...")`.
+///
+/// ```ignore
+/// let result: Result<(), std::io::Error> = measured!("read-config", { path => "demo.toml" }, {
+///     Ok(())
+/// });
+/// ```
+///
+macro_rules! measured {
+    ($name:expr, { $($key:ident => $value:expr),* $(,)? }, $body:block) => {{
+        use opentelemetry::{global, trace::{Status, TraceContextExt, Tracer}, KeyValue};
+
+        let tracer = global::tracer(module_path!());
+        let meter = global::meter(module_path!());
+        let histogram = meter.f64_histogram($name).build();
+        let attributes = vec![$( KeyValue::new(stringify!($key), $value) ),*];
+
+        tracer.in_span($name, |cx| {
+            let span = cx.span();
+            for attribute in &attributes {
+                span.set_attribute(attribute.clone());
+            }
+            let start = std::time::Instant::now();
+            let result = (|| $body)();
+            histogram.record(start.elapsed().as_secs_f64(), &attributes);
+            match &result {
+                Ok(_) => span.set_status(Status::Ok),
+                Err(err) => span.set_status(Status::error(err.to_string())),
+            }
+            result
+        })
+    }};
+}
+pub(crate) use measured;
@@ -6,14 +6,41 @@
 //! - the OpenTelemetry observability framework
 //! - the OpenTelemetry SDK
 //! - the OpenTelemetry exporter for standard output
+//! - the OpenTelemetry exporter for OTLP
 //! - the OpenTelemetry appender for the tracing crate
-//! 
+//!
 //! The `init_tracer_provider`, `init_meter_provider`, and `init_logger_provider` functions are called to
-//! initialize the respective components.
+//! initialize the respective components, and return a `Result` so a malformed
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` (or any other exporter build failure) is
+//! reported through `main`'s `?` instead of panicking.
+//!
+//! Each component defaults to an exporter that outputs to standard output. Set
+//! `OTEL_EXPORTER=otlp` (and optionally `OTEL_EXPORTER_OTLP_ENDPOINT`) to send
+//! telemetry to an OpenTelemetry Collector instead. The OTLP path depends on
+//! the `opentelemetry-otlp` crate with its `tonic`/gRPC transport feature
+//! enabled. No `Cargo.toml` is tracked anywhere in this source tree, so this
+//! is not a gap unique to OTLP: every dependency used here, including the
+//! core `opentelemetry`/`opentelemetry_sdk` crates, still needs to be added
+//! wherever this crate is actually packaged and built.
+//!
+//! `OTEL_EXPORTER_OTLP_TIMEOUT` bounds how long an OTLP export may take before
+//! it is given up on. `OTEL_METRIC_EXPORT_INTERVAL` controls how often the
+//! meter provider's `PeriodicReader` collects and exports metrics.
+//!
+//! The tracer and logger providers default to exporting one record at a time.
+//! Set `OTEL_PROCESSOR=batch` to buffer records and export them in batches on
+//! a background task instead, which is the production-recommended mode.
+//!
+//! `init_propagator` installs a W3C Trace Context propagator so spans can
+//! join a trace that started in another service, via the `inject_context`
+//! and `extract_context` helpers.
 //!
-//! Each component is configured with a default exporter that outputs to standard output.
-//! 
 //! The `global` module is used to set the meter provider for the application.
+//!
+//! The `macros` module provides `traced!`/`measured!` helpers for wrapping a
+//! block of code in a span, and optionally a duration histogram, in one line.
+
+mod macros;
 
 /// The opentelemetry::global module in OpenTelemetry provides functions for
 /// managing global instances of a tracer provider and meter provider.
@@ -36,76 +63,427 @@ use opentelemetry_sdk::Resource;
 ///
 use tracing_subscriber::prelude::*;
 
-/// Create a static resource that will be used for all telemetry data.
-/// 
-/// The `RESOURCE` provides metadata about the service that is generating telemetry data.
-/// This resource includes the service name and can be extended with additional attributes.
-/// This is useful for identifying the source of telemetry data in a distributed system.
-/// The `LazyLock` ensures that the resource is initialized only once and is thread-safe.
-/// 
-static RESOURCE: std::sync::LazyLock<Resource> = std::sync::LazyLock::new(|| {
-    Resource::builder()
-        .with_service_name("demo-rust-opentelemetry-stdout")
-        .build()
-});
+/// Which exporter backend the providers should send telemetry to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exporter {
+    /// Print telemetry to standard output. The default.
+    Stdout,
+    /// Send telemetry to an OpenTelemetry Collector over OTLP/gRPC.
+    Otlp,
+}
 
+/// Which processor the tracer and logger providers should use to hand records
+/// off to the exporter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Processor {
+    /// Export each span/log one at a time on the calling thread. The default.
+    /// Simple and predictable, but the opposite of production guidance.
+    Simple,
+    /// Buffer spans/logs and export them in batches on a background task.
+    Batch,
+}
 
-/// Initialize OpenTelemetry tracer provider. 
-/// 
-/// This uses the OpenTelemetry SDK and OpenTelemetry exporter for stdout.
-/// 
-/// This processor directly exports data to the configured exporter whenever a
-/// span is completed. This happens because of `with_simple_exporter`.
-/// 
+/// Centralized configuration for the demo, parsed once from environment
+/// variables at startup.
+///
+/// This mirrors how real deployments configure observability: via environment
+/// variables rather than recompiling with different constants. `main` builds
+/// one `Config` and passes it to each `init_*_provider` function.
+///
+#[derive(Debug, Clone)]
+struct Config {
+    /// The `service.name` resource attribute. Read from `OTEL_SERVICE_NAME`.
+    service_name: String,
+    /// Additional resource attributes, read from `OTEL_RESOURCE_ATTRIBUTES` as
+    /// a comma-separated list of `key=value` pairs.
+    resource_attributes: Vec<opentelemetry::KeyValue>,
+    /// Which exporter backend to use. Read from `OTEL_EXPORTER`.
+    exporter: Exporter,
+    /// The OTLP collector endpoint. Read from `OTEL_EXPORTER_OTLP_ENDPOINT`.
+    otlp_endpoint: String,
+    /// How long to wait for an OTLP export to complete before giving up. Read
+    /// from `OTEL_EXPORTER_OTLP_TIMEOUT` as a number of milliseconds.
+    export_timeout: std::time::Duration,
+    /// How often the meter provider's periodic reader collects and exports
+    /// metrics. Read from `OTEL_METRIC_EXPORT_INTERVAL` as a number of
+    /// milliseconds.
+    metric_export_interval: std::time::Duration,
+    /// Which processor the tracer and logger providers should use. Read from
+    /// `OTEL_PROCESSOR`.
+    processor: Processor,
+    /// The maximum number of spans buffered before the batch span processor
+    /// starts dropping records. Read from `OTEL_BSP_MAX_QUEUE_SIZE`.
+    trace_batch_max_queue_size: usize,
+    /// The maximum number of spans exported in a single batch. Read from
+    /// `OTEL_BSP_MAX_EXPORT_BATCH_SIZE`.
+    trace_batch_max_export_batch_size: usize,
+    /// How long the batch span processor waits between export attempts. Read
+    /// from `OTEL_BSP_SCHEDULE_DELAY` as a number of milliseconds.
+    trace_batch_scheduled_delay: std::time::Duration,
+    /// The maximum number of logs buffered before the batch log processor
+    /// starts dropping records. Read from `OTEL_BLRP_MAX_QUEUE_SIZE`.
+    log_batch_max_queue_size: usize,
+    /// The maximum number of logs exported in a single batch. Read from
+    /// `OTEL_BLRP_MAX_EXPORT_BATCH_SIZE`.
+    log_batch_max_export_batch_size: usize,
+    /// How long the batch log processor waits between export attempts. Read
+    /// from `OTEL_BLRP_SCHEDULE_DELAY` as a number of milliseconds.
+    log_batch_scheduled_delay: std::time::Duration,
+}
+
+impl Config {
+    /// Parse configuration from environment variables, falling back to
+    /// sensible defaults for a local demo when a variable is unset or
+    /// malformed.
+    ///
+    fn from_env() -> Self {
+        let service_name = std::env::var("OTEL_SERVICE_NAME")
+            .unwrap_or_else(|_| "demo-rust-opentelemetry-stdout".into());
+        let resource_attributes = std::env::var("OTEL_RESOURCE_ATTRIBUTES")
+            .map(|raw| parse_resource_attributes(&raw))
+            .unwrap_or_default();
+        let exporter = match std::env::var("OTEL_EXPORTER").as_deref() {
+            Ok("otlp") => Exporter::Otlp,
+            _ => Exporter::Stdout,
+        };
+        let otlp_endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .unwrap_or_else(|_| "http://localhost:4317".into());
+        let export_timeout = parse_duration_millis("OTEL_EXPORTER_OTLP_TIMEOUT", 10_000);
+        let metric_export_interval = parse_duration_millis("OTEL_METRIC_EXPORT_INTERVAL", 60_000);
+        let processor = match std::env::var("OTEL_PROCESSOR").as_deref() {
+            Ok("batch") => Processor::Batch,
+            _ => Processor::Simple,
+        };
+        let trace_batch_max_queue_size = std::env::var("OTEL_BSP_MAX_QUEUE_SIZE")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(2048);
+        let trace_batch_max_export_batch_size = std::env::var("OTEL_BSP_MAX_EXPORT_BATCH_SIZE")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(512);
+        let trace_batch_scheduled_delay = std::env::var("OTEL_BSP_SCHEDULE_DELAY")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .map(std::time::Duration::from_millis)
+            .unwrap_or_else(|| std::time::Duration::from_millis(5000));
+        let log_batch_max_queue_size = std::env::var("OTEL_BLRP_MAX_QUEUE_SIZE")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(2048);
+        let log_batch_max_export_batch_size = std::env::var("OTEL_BLRP_MAX_EXPORT_BATCH_SIZE")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .unwrap_or(512);
+        let log_batch_scheduled_delay = std::env::var("OTEL_BLRP_SCHEDULE_DELAY")
+            .ok()
+            .and_then(|raw| raw.parse().ok())
+            .map(std::time::Duration::from_millis)
+            .unwrap_or_else(|| std::time::Duration::from_millis(5000));
+        Self {
+            service_name,
+            resource_attributes,
+            exporter,
+            otlp_endpoint,
+            export_timeout,
+            metric_export_interval,
+            processor,
+            trace_batch_max_queue_size,
+            trace_batch_max_export_batch_size,
+            trace_batch_scheduled_delay,
+            log_batch_max_queue_size,
+            log_batch_max_export_batch_size,
+            log_batch_scheduled_delay,
+        }
+    }
+
+    /// Build the `Resource` that describes the telemetry source, combining the
+    /// configured service name with any additional resource attributes.
+    ///
+    fn resource(&self) -> Resource {
+        Resource::builder()
+            .with_service_name(self.service_name.clone())
+            .with_attributes(self.resource_attributes.clone())
+            .build()
+    }
+
+    /// Build the `BatchConfig` for the batch span processor, from the
+    /// `OTEL_BSP_*` fields.
+    ///
+    fn trace_batch_config(&self) -> opentelemetry_sdk::trace::BatchConfig {
+        opentelemetry_sdk::trace::BatchConfigBuilder::default()
+            .with_max_queue_size(self.trace_batch_max_queue_size)
+            .with_max_export_batch_size(self.trace_batch_max_export_batch_size)
+            .with_scheduled_delay(self.trace_batch_scheduled_delay)
+            .build()
+    }
+
+    /// Build the `BatchConfig` for the batch log processor, from the
+    /// `OTEL_BLRP_*` fields.
+    ///
+    fn log_batch_config(&self) -> opentelemetry_sdk::logs::BatchConfig {
+        opentelemetry_sdk::logs::BatchConfigBuilder::default()
+            .with_max_queue_size(self.log_batch_max_queue_size)
+            .with_max_export_batch_size(self.log_batch_max_export_batch_size)
+            .with_scheduled_delay(self.log_batch_scheduled_delay)
+            .build()
+    }
+}
+
+/// Parse `var` as a number of milliseconds, falling back to `default_millis`
+/// when the variable is unset or malformed.
+///
+fn parse_duration_millis(var: &str, default_millis: u64) -> std::time::Duration {
+    std::env::var(var)
+        .ok()
+        .and_then(|raw| raw.parse().ok())
+        .map(std::time::Duration::from_millis)
+        .unwrap_or_else(|| std::time::Duration::from_millis(default_millis))
+}
+
+/// Parse `OTEL_RESOURCE_ATTRIBUTES`-style `key=value,key=value` pairs into
+/// `KeyValue`s. Pairs that do not contain an `=` are skipped.
+///
+fn parse_resource_attributes(raw: &str) -> Vec<opentelemetry::KeyValue> {
+    raw.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| {
+            opentelemetry::KeyValue::new(key.trim().to_string(), value.trim().to_string())
+        })
+        .collect()
+}
+
+/// Initialize OpenTelemetry tracer provider.
+///
+/// This uses the OpenTelemetry SDK, exporting either to stdout or, when
+/// `config.exporter` is `Exporter::Otlp`, to an OpenTelemetry Collector over
+/// OTLP/gRPC at `config.otlp_endpoint`.
+///
+/// By default, this processor directly exports data to the configured
+/// exporter whenever a span is completed, via `with_simple_exporter`. When
+/// `config.processor` is `Processor::Batch`, spans are instead buffered and
+/// exported on a background task via a `BatchSpanProcessor`, using the queue
+/// size, batch size, and scheduled delay from `config`. Spans still buffered
+/// when the program exits are exported only if `force_flush` or `shutdown`
+/// is called first, so callers using batch mode must call one of them before
+/// exiting.
+///
 /// This function also sets the global tracer provider.
-/// 
-fn init_tracer_provider() -> opentelemetry_sdk::trace::SdkTracerProvider {
-    let exporter = opentelemetry_stdout::SpanExporter::default();
-    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
-        .with_simple_exporter(exporter)
-        .with_resource(RESOURCE.clone())
-        .build();
+///
+/// Returns an error if the OTLP exporter fails to build, e.g. because
+/// `config.otlp_endpoint` is malformed.
+///
+fn init_tracer_provider(
+    config: &Config,
+) -> Result<opentelemetry_sdk::trace::SdkTracerProvider, Box<dyn std::error::Error>> {
+    use opentelemetry_sdk::trace::BatchSpanProcessor;
+
+    let builder = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_resource(config.resource());
+    let batch_config = config.trace_batch_config();
+    let provider = if config.exporter == Exporter::Otlp {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(&config.otlp_endpoint)
+            .with_timeout(config.export_timeout)
+            .build()?;
+        if config.processor == Processor::Batch {
+            let processor = BatchSpanProcessor::builder(exporter)
+                .with_batch_config(batch_config)
+                .build();
+            builder.with_span_processor(processor).build()
+        } else {
+            builder.with_simple_exporter(exporter).build()
+        }
+    } else {
+        let exporter = opentelemetry_stdout::SpanExporter::default();
+        if config.processor == Processor::Batch {
+            let processor = BatchSpanProcessor::builder(exporter)
+                .with_batch_config(batch_config)
+                .build();
+            builder.with_span_processor(processor).build()
+        } else {
+            builder.with_simple_exporter(exporter).build()
+        }
+    };
     global::set_tracer_provider(provider.clone());
-    provider
+    Ok(provider)
+}
+
+/// Histogram instruments that should use explicit bucket boundaries instead
+/// of the SDK's default layout, as `(instrument_name, boundaries)` pairs.
+///
+/// `emit_metrics`'s `function-emit-metrics-histogram` records values that
+/// cluster well below the default boundaries (`0 to 5`, `5 to 10`, `10 to
+/// 25`, …), so a tighter layout gives more useful resolution.
+///
+const HISTOGRAM_BUCKET_BOUNDARIES: &[(&str, &[f64])] =
+    &[("function-emit-metrics-histogram", &[1.0, 2.0, 5.0, 10.0])];
+
+/// Build a `View` that overrides the named histogram instrument's aggregation
+/// to use explicit bucket `boundaries` instead of the SDK default.
+///
+fn histogram_view(
+    instrument_name: &str,
+    boundaries: Vec<f64>,
+) -> Box<dyn opentelemetry_sdk::metrics::View> {
+    use opentelemetry_sdk::metrics::{new_view, Aggregation, Instrument, Stream};
+
+    let selector = Instrument::new().name(instrument_name);
+    let stream = Stream::new().aggregation(Aggregation::ExplicitBucketHistogram {
+        boundaries,
+        record_min_max: true,
+    });
+    new_view(selector, stream).expect("failed to build histogram view")
 }
 
 /// Initialize OpenTelemetry meter provider.
-/// 
-/// This uses the OpenTelemetry SDK and OpenTelemetry exporter for stdout.
-/// 
-/// This processor collects metrics based on a time interval and then sends them
-/// to the configured exporter.  This happens because of `with_periodic exporter`.
-/// 
+///
+/// This uses the OpenTelemetry SDK, exporting either to stdout or, when
+/// `config.exporter` is `Exporter::Otlp`, to an OpenTelemetry Collector over
+/// OTLP/gRPC at `config.otlp_endpoint`.
+///
+/// This processor collects metrics on a `PeriodicReader`, which exports them
+/// to the configured exporter every `config.metric_export_interval`, aborting
+/// an export that runs longer than `config.export_timeout`.
+///
+/// Registers a `View` for each entry in `HISTOGRAM_BUCKET_BOUNDARIES` so those
+/// histograms report explicit bucket boundaries tailored to their expected
+/// value distribution, instead of the SDK's default layout.
+///
 /// This function also sets the global meter provider.
-/// 
-fn init_meter_provider() -> opentelemetry_sdk::metrics::SdkMeterProvider {
-    let exporter = opentelemetry_stdout::MetricExporter::default();
-    let provider = SdkMeterProvider::builder()
-        .with_periodic_exporter(exporter)
-        .with_resource(RESOURCE.clone())
-        .build();
+///
+/// Returns an error if the OTLP exporter fails to build, e.g. because
+/// `config.otlp_endpoint` is malformed.
+///
+fn init_meter_provider(
+    config: &Config,
+) -> Result<opentelemetry_sdk::metrics::SdkMeterProvider, Box<dyn std::error::Error>> {
+    use opentelemetry_sdk::metrics::PeriodicReader;
+
+    let mut builder = SdkMeterProvider::builder().with_resource(config.resource());
+    for (instrument_name, boundaries) in HISTOGRAM_BUCKET_BOUNDARIES {
+        builder = builder.with_view(histogram_view(instrument_name, boundaries.to_vec()));
+    }
+    let provider = if config.exporter == Exporter::Otlp {
+        let exporter = opentelemetry_otlp::MetricExporter::builder()
+            .with_tonic()
+            .with_endpoint(&config.otlp_endpoint)
+            .with_timeout(config.export_timeout)
+            .build()?;
+        let reader = PeriodicReader::builder(exporter)
+            .with_interval(config.metric_export_interval)
+            .with_timeout(config.export_timeout)
+            .build();
+        builder.with_reader(reader).build()
+    } else {
+        let exporter = opentelemetry_stdout::MetricExporter::default();
+        let reader = PeriodicReader::builder(exporter)
+            .with_interval(config.metric_export_interval)
+            .with_timeout(config.export_timeout)
+            .build();
+        builder.with_reader(reader).build()
+    };
     global::set_meter_provider(provider.clone());
-    provider
+    Ok(provider)
 }
 
 /// Initialize OpenTelemetry logger provider.
-/// 
-/// This uses the OpenTelemetry SDK and OpenTelemetry exporter for stdout.
-/// 
+///
+/// This uses the OpenTelemetry SDK, exporting either to stdout or, when
+/// `config.exporter` is `Exporter::Otlp`, to an OpenTelemetry Collector over
+/// OTLP/gRPC at `config.otlp_endpoint`.
+///
 /// This function creates a bridge from the tracing subscriber to the
 /// OpenTelemetry export, by registering the bridge with tracing_subscriber.
-/// 
-fn init_logger_provider() -> opentelemetry_sdk::logs::SdkLoggerProvider {
+///
+/// By default logs are exported one at a time, via `with_simple_exporter`.
+/// When `config.processor` is `Processor::Batch`, logs are instead buffered
+/// and exported on a background task via a `BatchLogProcessor`, using the
+/// queue size, batch size, and scheduled delay from `config`. Logs still
+/// buffered when the program exits are exported only if `force_flush` or
+/// `shutdown` is called first, so callers using batch mode must call one of
+/// them before exiting.
+///
+/// Returns an error if the OTLP exporter fails to build, e.g. because
+/// `config.otlp_endpoint` is malformed.
+///
+fn init_logger_provider(
+    config: &Config,
+) -> Result<opentelemetry_sdk::logs::SdkLoggerProvider, Box<dyn std::error::Error>> {
     use opentelemetry_appender_tracing::layer;
+    use opentelemetry_sdk::logs::BatchLogProcessor;
 
-    let exporter = opentelemetry_stdout::LogExporter::default();
-    let provider = opentelemetry_sdk::logs::SdkLoggerProvider::builder()
-        .with_simple_exporter(exporter)
-        .with_resource(RESOURCE.clone())
-        .build();
+    let builder = opentelemetry_sdk::logs::SdkLoggerProvider::builder()
+        .with_resource(config.resource());
+    let batch_config = config.log_batch_config();
+    let provider = if config.exporter == Exporter::Otlp {
+        let exporter = opentelemetry_otlp::LogExporter::builder()
+            .with_tonic()
+            .with_endpoint(&config.otlp_endpoint)
+            .with_timeout(config.export_timeout)
+            .build()?;
+        if config.processor == Processor::Batch {
+            let processor = BatchLogProcessor::builder(exporter)
+                .with_batch_config(batch_config)
+                .build();
+            builder.with_log_processor(processor).build()
+        } else {
+            builder.with_simple_exporter(exporter).build()
+        }
+    } else {
+        let exporter = opentelemetry_stdout::LogExporter::default();
+        if config.processor == Processor::Batch {
+            let processor = BatchLogProcessor::builder(exporter)
+                .with_batch_config(batch_config)
+                .build();
+            builder.with_log_processor(processor).build()
+        } else {
+            builder.with_simple_exporter(exporter).build()
+        }
+    };
     let layer = layer::OpenTelemetryTracingBridge::new(&provider);
     tracing_subscriber::registry().with(layer).init();
-    provider
+    Ok(provider)
+}
+
+/// Install a W3C Trace Context propagator as the global text-map propagator.
+///
+/// Without this, the demo never installs a propagator, so every span starts
+/// a fresh trace with `ParentSpanId: 0000000000000000` even if it is really
+/// continuing work from another service. Composes the trace-context
+/// propagator with a baggage propagator so baggage key/value pairs travel
+/// across the wire alongside the trace context.
+///
+fn init_propagator() {
+    use opentelemetry::propagation::TextMapCompositePropagator;
+    use opentelemetry_sdk::propagation::{BaggagePropagator, TraceContextPropagator};
+
+    let propagator = TextMapCompositePropagator::new(vec![
+        Box::new(TraceContextPropagator::new()),
+        Box::new(BaggagePropagator::new()),
+    ]);
+    global::set_text_map_propagator(propagator);
+}
+
+/// Inject the current context's trace information into a carrier, e.g. to
+/// send as `traceparent`/`baggage` HTTP headers to a downstream service.
+///
+fn inject_context(cx: &opentelemetry::Context) -> std::collections::HashMap<String, String> {
+    use opentelemetry::propagation::TextMapPropagator;
+
+    let mut carrier = std::collections::HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(cx, &mut carrier));
+    carrier
+}
+
+/// Extract a context from a carrier, e.g. the `traceparent`/`baggage` headers
+/// of an incoming request, so a new span can continue that trace.
+///
+fn extract_context(carrier: &std::collections::HashMap<String, String>) -> opentelemetry::Context {
+    use opentelemetry::propagation::TextMapPropagator;
+
+    global::get_text_map_propagator(|propagator| propagator.extract(carrier))
 }
 
 /// Example of how to emit a log entry.
@@ -147,7 +525,7 @@ fn emit_log() {
 ///    Instrumentation Scope: InstrumentationScope { name: "", version: None, schema_url: None, attributes: [] }
 ///    EventName: "function-emit-span-my-name"
 ///    Target (Scope): "function-emit-span-my-target"
-///    TraceId: 9539f563bbf57a7abe51081ec0b47592
+///    TraceId: 0af7651916cd43dd8448eb211c80319c
 ///    SpanId: 3304d306a2c81b88
 ///    TraceFlags: TraceFlags(1)
 ///    Observed Timestamp: 2025-07-22 07:31:01.332568
@@ -181,10 +559,10 @@ fn emit_log() {
 ///        ->  scope_key: scope_value
 /// 
 ///   Name        : example-span
-///   TraceId     : aa547ec795748c1b04869219b6defa31
+///   TraceId     : 0af7651916cd43dd8448eb211c80319c
 ///   SpanId      : 2cf3c5dc13e2eef5
 ///   TraceFlags  : TraceFlags(1)
-///   ParentSpanId: 0000000000000000
+///   ParentSpanId: b7ad6b7169203331
 ///   Kind        : Internal
 ///   Start time: 2025-07-22 06:55:12.957518
 ///   End time: 2025-07-22 06:55:12.957560
@@ -198,11 +576,26 @@ fn emit_log() {
 ///   Attributes:
 ///      ->  event_attribute1: String(Static("event_value1"))
 /// ```
-/// 
+///
+/// This also demonstrates continuing a trace from an incoming request: a
+/// carrier holding a `traceparent` header is extracted into a `Context` via
+/// `extract_context`, then attached so `example-span`'s `ParentSpanId`
+/// matches the incoming span instead of being zeroed.
+///
 fn emit_span() {
     use opentelemetry::{trace::{Tracer, TraceContextExt}, InstrumentationScope};
     use opentelemetry::KeyValue;
 
+    // Simulate an incoming request carrying a traceparent header from an
+    // upstream service, so this span continues that trace.
+    let mut incoming_headers = std::collections::HashMap::new();
+    incoming_headers.insert(
+        "traceparent".to_string(),
+        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".to_string(),
+    );
+    let parent_cx = extract_context(&incoming_headers);
+    let _parent_cx_guard = parent_cx.attach();
+
     let scope = InstrumentationScope::builder("stdout-example")
         .with_version("v1")
         .with_attributes([
@@ -225,6 +618,12 @@ fn emit_span() {
             user_name = "function-emit-span-my-target-user-name", // e.g. "otel"
             user_email = "function-emit-span-my-user-email" // e.g. "otel@opentelemetry.io"
         );
+
+        // Inject this span's context into a carrier, as if making an
+        // outgoing call to a downstream service that should continue this
+        // trace.
+        let outgoing_headers = inject_context(&cx);
+        debug_assert!(outgoing_headers.contains_key("traceparent"));
     })
 }
 
@@ -331,8 +730,9 @@ fn emit_span() {
 ///          ->  color: red
 /// ```
 /// 
-/// The histogram output looks like:
-/// 
+/// The histogram output looks like, with buckets reflecting the explicit
+/// `[1.0, 2.0, 5.0, 10.0]` boundaries registered in `HISTOGRAM_BUCKET_BOUNDARIES`:
+///
 /// ```stdout
 /// Metric #1
 ///     Name         : function-emit-metrics-histogram
@@ -352,11 +752,11 @@ fn emit_span() {
 ///          ->  name: banana
 ///          ->  color: yellow
 ///       Buckets
-///          -inf to 0 : 0
-///          0 to 5 : 1
+///          -inf to 1 : 1
+///          1 to 2 : 0
+///          2 to 5 : 0
 ///          5 to 10 : 0
-///          10 to 25 : 1
-///          …
+///          10 to +inf : 1
 ///     DataPoint #1
 ///       Count        : 1
 ///       Sum          : 2.0
@@ -366,10 +766,11 @@ fn emit_span() {
 ///          ->  name: apple
 ///          ->  color: red
 ///       Buckets
-///          -inf to 0 : 0
-///          0 to 5 : 1
+///          -inf to 1 : 0
+///          1 to 2 : 1
+///          2 to 5 : 0
 ///          5 to 10 : 0
-///          …
+///          10 to +inf : 0
 ///     DataPoint #2
 ///       Count        : 2
 ///       Sum          : 2.0
@@ -379,10 +780,11 @@ fn emit_span() {
 ///          ->  name: apple
 ///          ->  color: green
 ///       Buckets
-///          -inf to 0 : 0
-///          0 to 5 : 2
+///          -inf to 1 : 2
+///          1 to 2 : 0
+///          2 to 5 : 0
 ///          5 to 10 : 0
-///          …
+///          10 to +inf : 0
 /// ```
 /// 
 fn emit_metrics() {
@@ -463,7 +865,21 @@ fn emit_metrics() {
     );
 }
 
-/// Demonstrate OpenTelemetry and how to emit a log, a span, and some metrics. 
+/// Example of how to use the `traced!`/`measured!` macros from the `macros`
+/// module, instead of opening a span and a histogram by hand the way
+/// `emit_span` and `emit_metrics` do above.
+///
+fn emit_traced_example() {
+    let _: Result<(), std::io::Error> = macros::traced!("function-emit-traced-example-traced", { user_name => "function-emit-traced-example-user-name" }, {
+        Ok(())
+    });
+
+    let _: Result<(), std::io::Error> = macros::measured!("function-emit-traced-example-measured", { user_name => "function-emit-traced-example-user-name" }, {
+        Ok(())
+    });
+}
+
+/// Demonstrate OpenTelemetry and how to emit a log, a span, and some metrics.
 /// 
 /// This main function does three things:
 /// 
@@ -508,17 +924,28 @@ fn emit_metrics() {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
+    // Parse configuration once, then share it across all providers.
+    let config = Config::from_env();
+
+    // Install the W3C Trace Context propagator so spans can join a trace
+    // started by another service.
+    init_propagator();
+
     // Initialize the providers.
-    let tracer_provider = init_tracer_provider();
-    let meter_provider = init_meter_provider();
-    let logger_provider = init_logger_provider();
+    let tracer_provider = init_tracer_provider(&config)?;
+    let meter_provider = init_meter_provider(&config)?;
+    let logger_provider = init_logger_provider(&config)?;
 
     // // Emit examples.
     emit_log();
     emit_span();
     emit_metrics();
+    emit_traced_example();
 
-    // Shut down the providers.
+    // Flush any records still buffered by a batch processor, then shut down
+    // the providers.
+    tracer_provider.force_flush()?;
+    logger_provider.force_flush()?;
     tracer_provider.shutdown()?;
     meter_provider.shutdown()?;
     logger_provider.shutdown()?;